@@ -8,17 +8,41 @@ use crate::{
     leader_schedule_cache::LeaderScheduleCache,
 };
 use log::*;
+use solana_download_utils::download_file;
 use solana_runtime::{
     bank_forks::{ArchiveFormat, BankForks, SnapshotConfig},
     snapshot_utils,
 };
 use solana_sdk::{clock::Slot, genesis_config::GenesisConfig, hash::Hash};
-use std::{fs, path::PathBuf, process, result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    result,
+};
+use thiserror::Error;
+
+/// Errors `load` and its helpers can return, in addition to whatever
+/// `blockstore_processor::process_blockstore[_from_root]` itself reports.
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error(transparent)]
+    BlockstoreProcessor(#[from] BlockstoreProcessorError),
+
+    #[error("account paths not present when booting from snapshot")]
+    MissingAccountPaths,
+
+    #[error("failed to deserialize snapshot archive {0:?}: {1}")]
+    SnapshotDeserializationFailed(PathBuf, String),
 
-pub type LoadResult = result::Result<
-    (BankForks, LeaderScheduleCache, Option<(Slot, Hash)>),
-    BlockstoreProcessorError,
->;
+    #[error("snapshot has mismatch:\narchive: {expected:?}\ndeserialized: {actual:?}")]
+    SnapshotSlotHashMismatch {
+        expected: (Slot, Hash),
+        actual: (Slot, Hash),
+    },
+}
+
+pub type LoadResult =
+    result::Result<(BankForks, LeaderScheduleCache, Option<(Slot, Hash)>), LoadError>;
 
 fn to_loadresult(
     bpr: BlockstoreProcessorResult,
@@ -27,6 +51,165 @@ fn to_loadresult(
     bpr.map(|(bank_forks, leader_schedule_cache)| {
         (bank_forks, leader_schedule_cache, snapshot_slot_and_hash)
     })
+    .map_err(LoadError::from)
+}
+
+/// Rejects a deserialized bank whose slot or accounts hash doesn't match the snapshot archive
+/// it was supposedly taken from. `context` identifies which check failed in the error log, since
+/// both `load_from_snapshot` and `load_from_incremental_snapshot` perform this check, the latter
+/// at two points (base bank and final bank).
+fn check_slot_and_hash(
+    context: &str,
+    actual: (Slot, Hash),
+    expected: (Slot, Hash),
+) -> result::Result<(), LoadError> {
+    if actual != expected {
+        error!(
+            "{} mismatch:\narchive: {:?}\ndeserialized: {:?}",
+            context, expected, actual
+        );
+        return Err(LoadError::SnapshotSlotHashMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// What `load` should do if the highest-ranked snapshot archive on disk fails to deserialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotLoadFailureAction {
+    /// Propagate the error to the caller.
+    Abort,
+    /// Retry with the next-highest snapshot archive, if any; fall back to genesis once no
+    /// archive is left to try.
+    FallbackToGenesis,
+}
+
+/// The newest snapshot archive a remote source is willing to hand out, as advertised ahead of
+/// the download itself.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RemoteSnapshotManifest {
+    pub slot: Slot,
+    pub hash: Hash,
+    pub archive_format: ArchiveFormat,
+}
+
+/// A pluggable source of snapshot archives, fetched into `snapshot_package_output_path` before
+/// `load` looks for a local archive to deserialize.
+pub trait SnapshotArchiveDownloader {
+    /// Returns the manifest of the newest archive the remote source can provide, if any.
+    fn advertised_snapshot(&self) -> result::Result<Option<RemoteSnapshotManifest>, String>;
+
+    /// Streams the archive described by `manifest` into `destination`, resuming a partial
+    /// download already present at that path.
+    fn download_into(
+        &self,
+        manifest: &RemoteSnapshotManifest,
+        destination: &Path,
+    ) -> result::Result<(), String>;
+}
+
+/// Fetches snapshot archives over HTTP from `rpc_addr`, resuming partial downloads the way
+/// `solana_download_utils::download_file` already does for genesis downloads.
+pub struct HttpSnapshotArchiveDownloader {
+    pub rpc_addr: String,
+}
+
+impl SnapshotArchiveDownloader for HttpSnapshotArchiveDownloader {
+    fn advertised_snapshot(&self) -> result::Result<Option<RemoteSnapshotManifest>, String> {
+        let manifest_url = format!("http://{}/snapshot.json", self.rpc_addr);
+        let response = reqwest::blocking::get(&manifest_url)
+            .map_err(|err| format!("Unable to fetch {}: {}", manifest_url, err))?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        let manifest: RemoteSnapshotManifest = response
+            .json()
+            .map_err(|err| format!("Unable to parse {}: {}", manifest_url, err))?;
+        Ok(Some(manifest))
+    }
+
+    fn download_into(
+        &self,
+        manifest: &RemoteSnapshotManifest,
+        destination: &Path,
+    ) -> result::Result<(), String> {
+        let archive_url = format!(
+            "http://{}/{}",
+            self.rpc_addr,
+            snapshot_utils::snapshot_archive_path(
+                &PathBuf::new(),
+                manifest.slot,
+                &manifest.hash,
+                manifest.archive_format,
+            )
+            .display()
+        );
+        download_file(&archive_url, destination, true)
+    }
+}
+
+/// Whether `maybe_download_snapshot_archive` should fetch a remote archive advertised at
+/// `remote_slot`, given the highest-slot archive already on disk (if any).
+fn should_download_snapshot(highest_local_slot: Option<Slot>, remote_slot: Slot) -> bool {
+    match highest_local_slot {
+        Some(highest_local_slot) => highest_local_slot < remote_slot,
+        None => true,
+    }
+}
+
+/// Fetches a snapshot archive via `downloader` into `snapshot_config`'s output path when no
+/// local archive matches or exceeds the advertised remote slot.
+///
+/// The download itself may be resumed, but a resumed-then-truncated download is still caught:
+/// `load_from_snapshot` re-derives the slot/hash of the deserialized bank and rejects it against
+/// the archive's filename exactly as it already does for locally produced archives, so a bad
+/// download can never silently become the running bank. A failed download is left in place
+/// rather than deleted, since `downloader.download_into` resumes a partial file already present
+/// at `destination`; deleting it here would throw away whatever bytes were already fetched and
+/// force the next attempt to restart from scratch.
+fn maybe_download_snapshot_archive(
+    downloader: &dyn SnapshotArchiveDownloader,
+    snapshot_config: &SnapshotConfig,
+    highest_local_slot: Option<Slot>,
+) {
+    let manifest = match downloader.advertised_snapshot() {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => {
+            info!("No snapshot archive advertised by the remote source");
+            return;
+        }
+        Err(err) => {
+            warn!("Unable to query remote snapshot source: {}", err);
+            return;
+        }
+    };
+
+    if !should_download_snapshot(highest_local_slot, manifest.slot) {
+        info!(
+            "Local snapshot at slot {} is already at least as new as the advertised remote \
+             snapshot at slot {}; skipping download",
+            highest_local_slot.unwrap(),
+            manifest.slot
+        );
+        return;
+    }
+
+    let destination = snapshot_utils::snapshot_archive_path(
+        &snapshot_config.snapshot_package_output_path,
+        manifest.slot,
+        &manifest.hash,
+        manifest.archive_format,
+    );
+    info!(
+        "Downloading snapshot archive for slot {} into {:?}",
+        manifest.slot, destination
+    );
+    if let Err(err) = downloader.download_into(&manifest, &destination) {
+        warn!(
+            "Failed to download snapshot archive: {}; leaving partial download at {:?} for the \
+             next attempt to resume",
+            err, destination
+        );
+    }
 }
 
 /// Load the banks and accounts
@@ -42,6 +225,8 @@ pub fn load(
     process_options: ProcessOptions,
     transaction_status_sender: Option<&TransactionStatusSender>,
     cache_block_meta_sender: Option<&CacheBlockMetaSender>,
+    snapshot_load_failure_action: SnapshotLoadFailureAction,
+    snapshot_archive_downloader: Option<&dyn SnapshotArchiveDownloader>,
 ) -> LoadResult {
     if let Some(snapshot_config) = snapshot_config.as_ref() {
         info!(
@@ -52,28 +237,72 @@ pub fn load(
         fs::create_dir_all(&snapshot_config.snapshot_path)
             .expect("Couldn't create snapshot directory");
 
-        if let Some((archive_filename, (archive_slot, archive_hash, archive_format))) =
-            snapshot_utils::get_highest_snapshot_archive_path(
+        if let Some(downloader) = snapshot_archive_downloader {
+            let highest_local_slot = snapshot_utils::get_highest_snapshot_archive_path(
                 &snapshot_config.snapshot_package_output_path,
             )
+            .map(|(_filename, (slot, _hash, _format))| slot);
+            maybe_download_snapshot_archive(downloader, snapshot_config, highest_local_slot);
+        }
+
+        for (archive_filename, (archive_slot, archive_hash, archive_format)) in
+            snapshot_utils::get_snapshot_archives(&snapshot_config.snapshot_package_output_path)
         {
-            return load_from_snapshot(
-                &genesis_config,
-                &blockstore,
-                account_paths,
-                shrink_paths,
-                snapshot_config,
-                process_options,
-                transaction_status_sender,
-                cache_block_meta_sender,
-                archive_filename,
+            let result = if let Some((
+                incremental_archive_filename,
+                (incremental_archive_slot, incremental_archive_hash, incremental_archive_format),
+            )) = snapshot_utils::get_highest_incremental_snapshot_archive_path(
+                &snapshot_config.snapshot_package_output_path,
                 archive_slot,
-                archive_hash,
-                archive_format,
-            );
-        } else {
-            info!("No snapshot package available; will load from genesis");
+            ) {
+                load_from_incremental_snapshot(
+                    &genesis_config,
+                    &blockstore,
+                    account_paths.clone(),
+                    shrink_paths.clone(),
+                    snapshot_config,
+                    process_options.clone(),
+                    transaction_status_sender,
+                    cache_block_meta_sender,
+                    archive_filename,
+                    archive_slot,
+                    archive_hash,
+                    archive_format,
+                    incremental_archive_filename,
+                    incremental_archive_slot,
+                    incremental_archive_hash,
+                    incremental_archive_format,
+                )
+            } else {
+                load_from_snapshot(
+                    &genesis_config,
+                    &blockstore,
+                    account_paths.clone(),
+                    shrink_paths.clone(),
+                    snapshot_config,
+                    process_options.clone(),
+                    transaction_status_sender,
+                    cache_block_meta_sender,
+                    archive_filename,
+                    archive_slot,
+                    archive_hash,
+                    archive_format,
+                )
+            };
+
+            match (result, snapshot_load_failure_action) {
+                (Ok(loaded), _) => return Ok(loaded),
+                (Err(err), SnapshotLoadFailureAction::Abort) => return Err(err),
+                (Err(err), SnapshotLoadFailureAction::FallbackToGenesis) => {
+                    warn!(
+                        "Failed to load snapshot at slot {}: {:?}; trying the next candidate",
+                        archive_slot, err
+                    );
+                }
+            }
         }
+
+        info!("No snapshot package available; will load from genesis");
     } else {
         info!("Snapshots disabled; will load from genesis");
     }
@@ -124,10 +353,9 @@ fn load_from_snapshot(
 ) -> LoadResult {
     info!("Loading snapshot package: {:?}", archive_filename);
 
-    // Fail hard here if snapshot fails to load, don't silently continue
     if account_paths.is_empty() {
         error!("Account paths not present when booting from snapshot");
-        process::exit(1);
+        return Err(LoadError::MissingAccountPaths);
     }
 
     let deserialized_bank = snapshot_utils::bank_from_archive(
@@ -144,7 +372,13 @@ fn load_from_snapshot(
         process_options.limit_load_slot_count_from_snapshot,
         process_options.shrink_ratio,
     )
-    .expect("Load from snapshot failed");
+    .map_err(|err| {
+        error!(
+            "Failed to load snapshot package {:?}: {}",
+            archive_filename, err
+        );
+        LoadError::SnapshotDeserializationFailed(archive_filename.clone(), err.to_string())
+    })?;
     if let Some(shrink_paths) = shrink_paths {
         deserialized_bank.set_shrink_paths(shrink_paths);
     }
@@ -158,14 +392,132 @@ fn load_from_snapshot(
         deserialized_bank.get_accounts_hash(),
     );
 
-    if deserialized_bank_slot_and_hash != (archive_slot, archive_hash) {
+    check_slot_and_hash(
+        "Snapshot has",
+        deserialized_bank_slot_and_hash,
+        (archive_slot, archive_hash),
+    )?;
+
+    to_loadresult(
+        blockstore_processor::process_blockstore_from_root(
+            blockstore,
+            deserialized_bank,
+            &process_options,
+            &VerifyRecyclers::default(),
+            transaction_status_sender,
+            cache_block_meta_sender,
+        ),
+        Some(deserialized_bank_slot_and_hash),
+    )
+}
+
+/// Load from a full snapshot archive, then replay an incremental snapshot archive's account
+/// delta on top of it before processing the blockstore.
+///
+/// The incremental archive's filename encodes the slot of the full snapshot it was taken
+/// against; `get_highest_incremental_snapshot_archive_path` only returns archives whose base
+/// slot matches `base_archive_slot`, but `bank_from_incremental_archive` re-checks this against
+/// the deserialized base bank's slot so a stale archive on disk can never be applied silently.
+#[allow(clippy::too_many_arguments)]
+fn load_from_incremental_snapshot(
+    genesis_config: &GenesisConfig,
+    blockstore: &Blockstore,
+    account_paths: Vec<PathBuf>,
+    shrink_paths: Option<Vec<PathBuf>>,
+    snapshot_config: &SnapshotConfig,
+    process_options: ProcessOptions,
+    transaction_status_sender: Option<&TransactionStatusSender>,
+    cache_block_meta_sender: Option<&CacheBlockMetaSender>,
+    base_archive_filename: PathBuf,
+    base_archive_slot: Slot,
+    base_archive_hash: Hash,
+    base_archive_format: ArchiveFormat,
+    incremental_archive_filename: PathBuf,
+    incremental_archive_slot: Slot,
+    incremental_archive_hash: Hash,
+    incremental_archive_format: ArchiveFormat,
+) -> LoadResult {
+    info!(
+        "Loading full snapshot package: {:?}, incremental snapshot package: {:?}",
+        base_archive_filename, incremental_archive_filename
+    );
+
+    if account_paths.is_empty() {
+        error!("Account paths not present when booting from snapshot");
+        return Err(LoadError::MissingAccountPaths);
+    }
+
+    let base_bank = snapshot_utils::bank_from_archive(
+        &account_paths,
+        &process_options.frozen_accounts,
+        &snapshot_config.snapshot_path,
+        &base_archive_filename,
+        base_archive_format,
+        genesis_config,
+        process_options.debug_keys.clone(),
+        Some(&crate::builtins::get(process_options.bpf_jit)),
+        process_options.account_indexes.clone(),
+        process_options.accounts_db_caching_enabled,
+        process_options.limit_load_slot_count_from_snapshot,
+        process_options.shrink_ratio,
+    )
+    .map_err(|err| {
+        error!(
+            "Failed to load full snapshot package {:?}: {}",
+            base_archive_filename, err
+        );
+        LoadError::SnapshotDeserializationFailed(base_archive_filename.clone(), err.to_string())
+    })?;
+
+    // Reject a base bank whose slot *or* hash doesn't match the full snapshot archive it was
+    // deserialized from, the same way the post-deserialize check below rejects a corrupt
+    // snapshot. Checking the slot alone would accept a full snapshot archive that happens to
+    // share a slot with the expected base but was produced from a different (or corrupted)
+    // account set.
+    let base_bank_slot_and_hash = (base_bank.slot(), base_bank.get_accounts_hash());
+    check_slot_and_hash(
+        "Incremental snapshot base",
+        base_bank_slot_and_hash,
+        (base_archive_slot, base_archive_hash),
+    )?;
+
+    let deserialized_bank = snapshot_utils::bank_from_incremental_archive(
+        &account_paths,
+        &snapshot_config.snapshot_path,
+        &incremental_archive_filename,
+        incremental_archive_format,
+        base_bank,
+    )
+    .map_err(|err| {
         error!(
-            "Snapshot has mismatch:\narchive: {:?}\ndeserialized: {:?}",
-            archive_hash, deserialized_bank_slot_and_hash
+            "Failed to load incremental snapshot package {:?}: {}",
+            incremental_archive_filename, err
         );
-        process::exit(1);
+        LoadError::SnapshotDeserializationFailed(
+            incremental_archive_filename.clone(),
+            err.to_string(),
+        )
+    })?;
+
+    if let Some(shrink_paths) = shrink_paths {
+        deserialized_bank.set_shrink_paths(shrink_paths);
+    }
+
+    if process_options.accounts_db_test_hash_calculation {
+        deserialized_bank.update_accounts_hash_with_index_option(false, true);
     }
 
+    let deserialized_bank_slot_and_hash = (
+        deserialized_bank.slot(),
+        deserialized_bank.get_accounts_hash(),
+    );
+
+    check_slot_and_hash(
+        "Snapshot has",
+        deserialized_bank_slot_and_hash,
+        (incremental_archive_slot, incremental_archive_hash),
+    )?;
+
     to_loadresult(
         blockstore_processor::process_blockstore_from_root(
             blockstore,
@@ -178,3 +530,36 @@ fn load_from_snapshot(
         Some(deserialized_bank_slot_and_hash),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_download_snapshot_prefers_newer_remote() {
+        assert!(should_download_snapshot(None, 100));
+        assert!(should_download_snapshot(Some(50), 100));
+        assert!(!should_download_snapshot(Some(100), 100));
+        assert!(!should_download_snapshot(Some(150), 100));
+    }
+
+    #[test]
+    fn check_slot_and_hash_accepts_matching_slot_and_hash() {
+        let slot_and_hash = (42, Hash::default());
+        assert!(check_slot_and_hash("test", slot_and_hash, slot_and_hash).is_ok());
+    }
+
+    #[test]
+    fn check_slot_and_hash_rejects_hash_mismatch_at_matching_slot() {
+        let expected = (42, Hash::default());
+        let actual = (42, Hash::new(&[1; 32]));
+        assert!(check_slot_and_hash("test", actual, expected).is_err());
+    }
+
+    #[test]
+    fn check_slot_and_hash_rejects_slot_mismatch() {
+        let expected = (42, Hash::default());
+        let actual = (43, Hash::default());
+        assert!(check_slot_and_hash("test", actual, expected).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+// Additions to the existing `snapshot_utils` module in support of incremental snapshots
+// (`get_highest_snapshot_archive_path`, `bank_from_archive`, and the other full-snapshot helpers
+// already referenced from `ledger::bank_forks_utils` live alongside these and are unchanged).
+
+use {
+    crate::{bank::Bank, bank_forks::ArchiveFormat},
+    lazy_static::lazy_static,
+    regex::Regex,
+    solana_sdk::{clock::Slot, hash::Hash},
+    std::{
+        fs,
+        io::{self, Read},
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    thiserror::Error,
+};
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("serialization error: {0}")]
+    Serialize(#[from] bincode::Error),
+
+    #[error(
+        "incremental snapshot base slot mismatch: archive expects base slot {expected}, but \
+         the deserialized base bank is at slot {actual}"
+    )]
+    BaseSlotMismatch { expected: Slot, actual: Slot },
+}
+
+lazy_static! {
+    static ref INCREMENTAL_SNAPSHOT_ARCHIVE_RE: Regex = Regex::new(
+        r"^incremental-snapshot-(?P<base>[[:digit:]]+)-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar|tar\.bz2|tar\.zst|tar\.gz|tar\.lz4)$"
+    )
+    .unwrap();
+    static ref FULL_SNAPSHOT_ARCHIVE_RE: Regex = Regex::new(
+        r"^snapshot-(?P<slot>[[:digit:]]+)-(?P<hash>[[:alnum:]]+)\.(?P<ext>tar|tar\.bz2|tar\.zst|tar\.gz|tar\.lz4)$"
+    )
+    .unwrap();
+}
+
+fn archive_format_from_ext(ext: &str) -> Option<ArchiveFormat> {
+    match ext {
+        "tar.bz2" => Some(ArchiveFormat::TarBzip2),
+        "tar.zst" => Some(ArchiveFormat::TarZstd),
+        "tar.gz" => Some(ArchiveFormat::TarGzip),
+        "tar.lz4" => Some(ArchiveFormat::TarLz4),
+        "tar" => Some(ArchiveFormat::Tar),
+        _ => None,
+    }
+}
+
+/// Finds the newest incremental snapshot archive in `snapshot_package_output_path` whose
+/// filename-encoded base slot equals `base_slot`.
+///
+/// The base slot is encoded in the filename so a mismatched incremental archive (one taken
+/// against a different full snapshot than the one `load` chose) is never even considered here;
+/// `load_from_incremental_snapshot` double-checks it again against the deserialized base bank's
+/// slot and accounts hash.
+pub fn get_highest_incremental_snapshot_archive_path(
+    snapshot_package_output_path: &Path,
+    base_slot: Slot,
+) -> Option<(PathBuf, (Slot, Hash, ArchiveFormat))> {
+    let mut archives: Vec<_> = fs::read_dir(snapshot_package_output_path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let file_name = path.file_name()?.to_str()?;
+            let captures = INCREMENTAL_SNAPSHOT_ARCHIVE_RE.captures(file_name)?;
+            let archive_base_slot: Slot = captures.name("base")?.as_str().parse().ok()?;
+            if archive_base_slot != base_slot {
+                return None;
+            }
+            let slot: Slot = captures.name("slot")?.as_str().parse().ok()?;
+            let hash: Hash = captures.name("hash")?.as_str().parse().ok()?;
+            let archive_format = archive_format_from_ext(captures.name("ext")?.as_str())?;
+            Some((path, (slot, hash, archive_format)))
+        })
+        .collect();
+    archives.sort_by_key(|(_, (slot, _, _))| *slot);
+    archives.pop()
+}
+
+fn extension_from_archive_format(archive_format: ArchiveFormat) -> &'static str {
+    match archive_format {
+        ArchiveFormat::TarBzip2 => "tar.bz2",
+        ArchiveFormat::TarZstd => "tar.zst",
+        ArchiveFormat::TarGzip => "tar.gz",
+        ArchiveFormat::TarLz4 => "tar.lz4",
+        ArchiveFormat::Tar => "tar",
+    }
+}
+
+/// The path a full snapshot archive for `slot`/`hash` lives (or should be written to) at under
+/// `snapshot_package_output_path`, matching the naming scheme `FULL_SNAPSHOT_ARCHIVE_RE` parses.
+pub fn snapshot_archive_path(
+    snapshot_package_output_path: &Path,
+    slot: Slot,
+    hash: &Hash,
+    archive_format: ArchiveFormat,
+) -> PathBuf {
+    snapshot_package_output_path.join(format!(
+        "snapshot-{}-{}.{}",
+        slot,
+        hash,
+        extension_from_archive_format(archive_format)
+    ))
+}
+
+/// Lists every full snapshot archive in `snapshot_package_output_path`, newest (highest slot)
+/// first.
+///
+/// `load` walks this list so that, when asked to fall back on failure, a full snapshot archive
+/// which fails to load (or whose incremental successor does) doesn't wedge the node: the
+/// next-highest candidate is tried, falling back to genesis once the list is exhausted. Otherwise
+/// `load` returns the first failure.
+pub fn get_snapshot_archives(
+    snapshot_package_output_path: &Path,
+) -> Vec<(PathBuf, (Slot, Hash, ArchiveFormat))> {
+    let mut archives: Vec<_> = fs::read_dir(snapshot_package_output_path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let file_name = path.file_name()?.to_str()?;
+            let captures = FULL_SNAPSHOT_ARCHIVE_RE.captures(file_name)?;
+            let slot: Slot = captures.name("slot")?.as_str().parse().ok()?;
+            let hash: Hash = captures.name("hash")?.as_str().parse().ok()?;
+            let archive_format = archive_format_from_ext(captures.name("ext")?.as_str())?;
+            Some((path, (slot, hash, archive_format)))
+        })
+        .collect();
+    archives.sort_by_key(|(_, (slot, _, _))| *slot);
+    archives.reverse();
+    archives
+}
+
+/// The bincode-serialized payload of an incremental snapshot archive: just the account storage
+/// entries created since `base_slot`, instead of a full account set.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct IncrementalSnapshotDelta {
+    base_slot: Slot,
+    slot: Slot,
+    storage_entry_paths: Vec<PathBuf>,
+}
+
+fn unpack_archive(
+    archive_path: &Path,
+    archive_format: ArchiveFormat,
+    unpack_dir: &Path,
+) -> Result<(), SnapshotError> {
+    let file = fs::File::open(archive_path)?;
+    let decoder: Box<dyn Read> = match archive_format {
+        ArchiveFormat::TarBzip2 => Box::new(bzip2::read::BzDecoder::new(file)),
+        ArchiveFormat::TarGzip => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarZstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        ArchiveFormat::TarLz4 => Box::new(lz4::Decoder::new(file)?),
+        ArchiveFormat::Tar => Box::new(file),
+    };
+    tar::Archive::new(decoder).unpack(unpack_dir)?;
+    Ok(())
+}
+
+/// Deserializes an incremental snapshot archive and applies its account delta on top of
+/// `base_bank`, mirroring `bank_from_archive`'s full-snapshot path but starting from an
+/// already-deserialized bank instead of genesis.
+///
+/// The returned bank is advanced to `delta.slot` (the same way ordinary block replay advances a
+/// bank to a child slot) and has its accounts hash recomputed before being returned: the caller
+/// immediately compares both against the incremental archive's filename-encoded slot and hash,
+/// so leaving the bank parked at `base_bank`'s slot/hash here would make that comparison fail on
+/// every incremental load.
+pub fn bank_from_incremental_archive(
+    account_paths: &[PathBuf],
+    snapshot_path: &Path,
+    incremental_archive_filename: &Path,
+    archive_format: ArchiveFormat,
+    base_bank: Bank,
+) -> Result<Bank, SnapshotError> {
+    let unpack_dir = snapshot_path.join("incremental-unpack");
+    let _ = fs::remove_dir_all(&unpack_dir);
+    fs::create_dir_all(&unpack_dir)?;
+    unpack_archive(incremental_archive_filename, archive_format, &unpack_dir)?;
+
+    let delta_file = unpack_dir.join("incremental-snapshot-delta");
+    let delta: IncrementalSnapshotDelta = bincode::deserialize_from(fs::File::open(&delta_file)?)?;
+
+    if delta.base_slot != base_bank.slot() {
+        return Err(SnapshotError::BaseSlotMismatch {
+            expected: delta.base_slot,
+            actual: base_bank.slot(),
+        });
+    }
+
+    let collector_id = *base_bank.collector_id();
+    let base_bank = Arc::new(base_bank);
+    let bank = Bank::new_from_parent(&base_bank, &collector_id, delta.slot);
+
+    bank.rc
+        .accounts
+        .accounts_db
+        .insert_new_storage_entries(account_paths, &delta.storage_entry_paths)?;
+    bank.force_flush_accounts_cache();
+
+    // Unlike the post-deserialize hash recompute in `load_from_snapshot`, this one can't be gated
+    // behind `accounts_db_test_hash_calculation`: the caller compares this hash against the
+    // incremental archive's filename-encoded hash right after this function returns, so it must
+    // always reflect the storage entries just inserted above.
+    bank.update_accounts_hash_with_index_option(false, false);
+    bank.freeze();
+
+    Ok(bank)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::genesis_config::create_genesis_config};
+
+    fn touch(dir: &Path, name: &str) {
+        fs::write(dir.join(name), []).unwrap();
+    }
+
+    fn write_incremental_archive(archive_path: &Path, delta: &IncrementalSnapshotDelta) {
+        let delta_bytes = bincode::serialize(delta).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(delta_bytes.len() as u64);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(fs::File::create(archive_path).unwrap());
+        builder
+            .append_data(&mut header, "incremental-snapshot-delta", &delta_bytes[..])
+            .unwrap();
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn get_highest_incremental_snapshot_archive_path_filters_by_base_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(
+            dir.path(),
+            "incremental-snapshot-100-110-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+        touch(
+            dir.path(),
+            "incremental-snapshot-100-120-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+        touch(
+            dir.path(),
+            "incremental-snapshot-200-210-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+
+        let (_path, (slot, _hash, _format)) =
+            get_highest_incremental_snapshot_archive_path(dir.path(), 100).unwrap();
+        assert_eq!(slot, 120);
+
+        assert!(get_highest_incremental_snapshot_archive_path(dir.path(), 300).is_none());
+    }
+
+    #[test]
+    fn get_snapshot_archives_sorts_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        touch(
+            dir.path(),
+            "snapshot-100-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+        touch(
+            dir.path(),
+            "snapshot-200-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+        touch(
+            dir.path(),
+            "snapshot-150-Bgyf4ztnmKtgZpV3AT2UxZRstGwjAb5Fw2WdqRsj9uBM.tar.zst",
+        );
+
+        let slots: Vec<Slot> = get_snapshot_archives(dir.path())
+            .into_iter()
+            .map(|(_path, (slot, _hash, _format))| slot)
+            .collect();
+        assert_eq!(slots, vec![200, 150, 100]);
+    }
+
+    #[test]
+    fn snapshot_archive_path_round_trips_through_get_snapshot_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let hash = Hash::new(&[7; 32]);
+        let path = snapshot_archive_path(dir.path(), 123, &hash, ArchiveFormat::TarZstd);
+        fs::write(&path, []).unwrap();
+
+        let archives = get_snapshot_archives(dir.path());
+        assert_eq!(archives.len(), 1);
+        let (found_path, (slot, found_hash, archive_format)) = &archives[0];
+        assert_eq!(found_path, &path);
+        assert_eq!(*slot, 123);
+        assert_eq!(*found_hash, hash);
+        assert_eq!(*archive_format, ArchiveFormat::TarZstd);
+    }
+
+    #[test]
+    fn bank_from_incremental_archive_advances_to_delta_slot() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(1_000_000);
+        let base_bank = Bank::new(&genesis_config);
+        let base_slot = base_bank.slot();
+        let delta_slot = base_slot + 10;
+
+        let snapshot_dir = tempfile::tempdir().unwrap();
+        let account_dir = tempfile::tempdir().unwrap();
+        let archive_path = snapshot_dir.path().join("incremental.tar");
+        write_incremental_archive(
+            &archive_path,
+            &IncrementalSnapshotDelta {
+                base_slot,
+                slot: delta_slot,
+                storage_entry_paths: vec![],
+            },
+        );
+
+        let bank = bank_from_incremental_archive(
+            &[account_dir.path().to_path_buf()],
+            snapshot_dir.path(),
+            &archive_path,
+            ArchiveFormat::Tar,
+            base_bank,
+        )
+        .unwrap();
+
+        // The bug this test guards against: returning `base_bank` unchanged would leave the
+        // bank at `base_slot` with the base snapshot's accounts hash, which can never match the
+        // incremental archive's own (higher) slot and (different) hash that the caller checks
+        // against immediately after this call.
+        assert_eq!(bank.slot(), delta_slot);
+        assert_ne!(bank.slot(), base_slot);
+    }
+}